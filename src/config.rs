@@ -0,0 +1,39 @@
+/// Runtime configuration shared between graph construction and rendering.
+#[derive(Debug, Clone)]
+pub struct Config<'o> {
+    pub build_lines: &'o str,
+    pub dev_lines: &'o str,
+    pub optional_lines: &'o str,
+    /// Patterns a node must match (against `name` or `name:version`) to be
+    /// kept. Empty means "keep everything" (no include filtering).
+    pub include: Vec<String>,
+    /// Patterns that drop a node when matched, checked after `include`.
+    pub exclude: Vec<String>,
+    /// Fill colors bucketed by BFS depth from the root (index 0 = root
+    /// itself, index 1 = direct deps, and so on). The last entry is reused
+    /// for every depth beyond the palette's length. Empty disables coloring.
+    pub depth_colors: Vec<String>,
+    /// Fill/edge color used to highlight nodes and edges that sit inside a
+    /// dependency cycle (a strongly-connected component of size > 1).
+    /// Takes precedence over `depth_colors` for those nodes.
+    pub cycle_color: String,
+}
+
+impl<'o> Config<'o> {
+    pub fn new() -> Self {
+        Config {
+            build_lines: "",
+            dev_lines: "",
+            optional_lines: "",
+            include: vec![],
+            exclude: vec![],
+            depth_colors: vec![
+                "lightblue".to_owned(),
+                "lightgreen".to_owned(),
+                "lightyellow".to_owned(),
+                "lightgray".to_owned(),
+            ],
+            cycle_color: "red".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use std::io::{self, Write};
+
+use config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepKind {
+    Normal,
+    Build,
+    Dev,
+    Optional,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedDep {
+    pub name: String,
+    pub ver: String,
+    kind: DepKind,
+}
+
+impl ResolvedDep {
+    pub fn new(name: String, ver: String) -> Self {
+        ResolvedDep {
+            name: name,
+            ver: ver,
+            kind: DepKind::Normal,
+        }
+    }
+
+    pub fn kind(&self) -> DepKind {
+        self.kind
+    }
+
+    pub fn label<W: Write>(&self, w: &mut W, _cfg: &Config, color: Option<&str>) -> io::Result<()> {
+        match color {
+            Some(c) => writeln!(w, " [label=\"{} {}\", style=filled, fillcolor=\"{}\"];", self.name, self.ver, c),
+            None => writeln!(w, " [label=\"{} {}\"];", self.name, self.ver),
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use std::fmt;
+use std::io;
+
+pub type CliResult<T> = Result<T, CliError>;
+
+#[derive(Debug)]
+pub enum CliError {
+    Io(io::Error),
+    Generic(String),
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::Io(ref e) => write!(f, "{}", e),
+            CliError::Generic(ref s) => write!(f, "{}", s),
+        }
+    }
+}
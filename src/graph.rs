@@ -1,4 +1,4 @@
-use std::env;
+use std::collections::VecDeque;
 use std::fmt;
 use std::io::{self, Write};
 
@@ -6,28 +6,82 @@ use config::Config;
 use dep::ResolvedDep;
 use error::CliResult;
 
+/// Returns true if `pattern` matches `node`'s name or its `name:version`
+/// form. Patterns use shell-style globbing: `*` matches any run of
+/// characters and `?` matches exactly one.
+fn pattern_matches(pattern: &str, node: &ResolvedDep) -> bool {
+    glob_match(pattern, &node.name) ||
+        glob_match(pattern, &format!("{}:{}", node.name, node.ver))
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<usize> = None;
+    let mut star_match = 0;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            star_match = ti;
+            pi += 1;
+        } else if let Some(si) = star {
+            pi = si + 1;
+            star_match += 1;
+            ti = star_match;
+        } else {
+            return false;
+        }
+    }
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+/// Which direction to follow edges when computing a focus subgraph.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Focus {
+    /// What the target crate pulls in: forward reachability from it.
+    Descendants,
+    /// Who depends on the target crate: reverse reachability from it.
+    Ancestors,
+    /// Both of the above, unioned.
+    Both,
+}
+
 pub type Nd = usize;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub struct Ed(pub Nd, pub Nd);
 
 impl Ed {
-    pub fn label<W: Write>(&self, w: &mut W, dg: &DepGraph) -> io::Result<()> {
+    pub fn label<W: Write>(&self, w: &mut W, dg: &DepGraph, cyclic: bool) -> io::Result<()> {
         use dep::DepKind::{Optional, Dev, Build};
         let parent = dg.get(self.0).unwrap().kind();
         let child = dg.get(self.1).unwrap().kind();
 
-        match (parent, child) {
-            (Build, Build) => writeln!(w, "[label=\"\"{}];", dg.cfg.build_lines),
-            (Build, Dev) => writeln!(w, "[label=\"\"{}];", dg.cfg.dev_lines),
-            (Build, Optional) => writeln!(w, "[label=\"\"{}];", dg.cfg.optional_lines),
-            (Optional, Build) => writeln!(w, "[label=\"\"{}];", dg.cfg.optional_lines),
-            (Optional, Dev) => writeln!(w, "[label=\"\"{}];", dg.cfg.optional_lines),
-            (Optional, Optional) => writeln!(w, "[label=\"\"{}];", dg.cfg.optional_lines),
-            (Dev, Build) => writeln!(w, "[label=\"\"{}];", dg.cfg.dev_lines),
-            (Dev, Dev) => writeln!(w, "[label=\"\"{}];", dg.cfg.dev_lines),
-            (Dev, Optional) => writeln!(w, "[label=\"\"{}];", dg.cfg.dev_lines),
-            _               => writeln!(w, "[label=\"\"];")
+        let extra = match (parent, child) {
+            (Build, Build) => dg.cfg.build_lines,
+            (Build, Dev) => dg.cfg.dev_lines,
+            (Build, Optional) => dg.cfg.optional_lines,
+            (Optional, Build) => dg.cfg.optional_lines,
+            (Optional, Dev) => dg.cfg.optional_lines,
+            (Optional, Optional) => dg.cfg.optional_lines,
+            (Dev, Build) => dg.cfg.dev_lines,
+            (Dev, Dev) => dg.cfg.dev_lines,
+            (Dev, Optional) => dg.cfg.dev_lines,
+            _               => "",
+        };
+
+        if cyclic {
+            writeln!(w, "[label=\"\"{}, color=\"{}\"];", extra, dg.cfg.cycle_color)
+        } else {
+            writeln!(w, "[label=\"\"{}];", extra)
         }
     }
 }
@@ -43,8 +97,23 @@ impl fmt::Display for Ed {
 pub struct DepGraph<'c, 'o>
     where 'o: 'c
 {
-    pub nodes: Vec<ResolvedDep>,
+    /// Node slots, indexed by stable id. A `None` slot is a tombstone left
+    /// behind by `remove`; ids of the nodes around it never shift. The only
+    /// place tombstones get swept out is `compact`, called once in
+    /// `render_to` after the pruning and filtering passes.
+    pub nodes: Vec<Option<ResolvedDep>>,
     pub edges: Vec<Ed>,
+    /// Id of the root node that `remove_orphans`/`depths_from_root` walk
+    /// from. Defaults to the first node added; `set_root` and `focus` move
+    /// it, `retain_cycles_only` moves it when the root itself was pruned, and
+    /// `compact` remaps it to its new dense id.
+    root: Nd,
+    /// Set by `retain_cycles_only`. A cycles-only graph is generally made up
+    /// of several components disjoint from `root` (the root crate is almost
+    /// never itself part of a cycle), so `render_to` must not run its normal
+    /// single-root orphan BFS, which would otherwise treat every retained
+    /// component as unreachable and wipe the whole graph.
+    cycles_only: bool,
     cfg: &'c Config<'o>,
 }
 
@@ -53,6 +122,8 @@ impl<'c, 'o> DepGraph<'c, 'o> {
         DepGraph {
             nodes: vec![],
             edges: vec![],
+            root: 0,
+            cycles_only: false,
             cfg: cfg,
         }
     }
@@ -64,79 +135,319 @@ impl<'c, 'o> DepGraph<'c, 'o> {
     }
 
     pub fn get(&self, id: usize) -> Option<&ResolvedDep> {
-        if id < self.nodes.len() {
-            return Some(&self.nodes[id]);
-        }
-        None
+        self.nodes.get(id).and_then(|slot| slot.as_ref())
     }
 
+    /// Tombstones node `id` and drops its incident edges, in one linear pass
+    /// over `edges`. Every other node keeps its id - nothing gets shifted.
     pub fn remove(&mut self, id: usize) {
         debugln!("remove; index={}", id);
-        self.nodes.remove(id);
-        // Remove edges of the removed node.
-        self.edges = self.edges.iter()
-            .filter(|e| !(e.0 == id || e.1 == id))
-            .cloned()
-            .collect();
-        self.shift_edges_after_node(id);
+        self.nodes[id] = None;
+        self.edges.retain(|e| !(e.0 == id || e.1 == id));
     }
 
-    fn shift_edges_after_node(&mut self, id: usize) {
-        enum Side {
-            Left,
-            Right,
-        }
-        let mut to_upd = vec![];
-        for c in id..self.nodes.len() {
-            for (eid, &Ed(idl, idr)) in self.edges.iter().enumerate() {
-                if idl == c { to_upd.push((eid, Side::Left, c-1)); }
-                if idr == c { to_upd.push((eid, Side::Right, c-1)); }
-            }
+    /// Builds a `parent -> children` adjacency list, indexed by node id, so
+    /// the BFS passes below can walk edges in O(V + E) instead of rescanning
+    /// all of `edges` on every dequeued node.
+    fn build_adjacency(&self) -> Vec<Vec<usize>> {
+        let mut adj = vec![vec![]; self.nodes.len()];
+        for &Ed(parent, child) in &self.edges {
+            adj[parent].push(child);
         }
-        for (eid, side, new) in to_upd {
-            match side {
-                Side::Left => self.edges[eid].0 = new,
-                Side::Right => self.edges[eid].1 = new,
-            }
+        adj
+    }
+
+    /// Like `build_adjacency`, but `child -> parents` - used to walk edges
+    /// backwards (e.g. for `focus`'s ancestors direction).
+    fn build_reverse_adjacency(&self) -> Vec<Vec<usize>> {
+        let mut adj = vec![vec![]; self.nodes.len()];
+        for &Ed(parent, child) in &self.edges {
+            adj[child].push(parent);
         }
+        adj
     }
 
+    /// Drops every node unreachable from `root` via a single BFS, instead of
+    /// the old fixpoint loop that rescanned and reindexed on every single
+    /// removal.
     pub fn remove_orphans(&mut self) {
         let len = self.nodes.len();
         self.edges.retain(|&Ed(idl,idr)| idl < len && idr < len);
         debugln!("remove_orphans; nodes={:?}", self.nodes);
-        loop {
-            let mut removed = false;
-            let mut used = vec![false; self.nodes.len()];
-            used[0] = true;
-            for &Ed(_, idr) in &self.edges {
-                debugln!("remove_orphans; idr={}", idr);
-                used[idr] = true;
+
+        let adj = self.build_adjacency();
+        let mut reachable = vec![false; len];
+        if self.root < len && self.nodes[self.root].is_some() {
+            reachable[self.root] = true;
+            let mut queue = VecDeque::new();
+            queue.push_back(self.root);
+            while let Some(cur) = queue.pop_front() {
+                for &child in &adj[cur] {
+                    if !reachable[child] {
+                        reachable[child] = true;
+                        queue.push_back(child);
+                    }
+                }
             }
-            debugln!("remove_orphans; unused_nodes={:?}", used);
-
-            for (id, &u) in used.iter().enumerate() {
-                if !u {
-                    debugln!("remove_orphans; removing={}", id);
-                    self.nodes.remove(id);
-
-                    // Remove edges originating from the removed node
-                    self.edges.retain(|&Ed(origin,_)| origin != id);
-                    // Adjust edges to match the new node indexes
-                    for edge in self.edges.iter_mut() {
-                        if edge.0 > id {
-                            edge.0 -= 1;
-                        }
-                        if edge.1 > id {
-                            edge.1 -= 1;
+        }
+        debugln!("remove_orphans; reachable={:?}", reachable);
+
+        for (id, &r) in reachable.iter().enumerate() {
+            if !r {
+                self.nodes[id] = None;
+            }
+        }
+        self.edges.retain(|&Ed(idl, idr)| reachable[idl] && reachable[idr]);
+    }
+
+    /// Drops every node that fails the configured include/exclude filters.
+    ///
+    /// A node is kept when it matches at least one `cfg.include` pattern (or
+    /// `cfg.include` is empty, meaning "no include filter") and matches none
+    /// of the `cfg.exclude` patterns.
+    fn apply_filters(&mut self) {
+        let to_remove: Vec<(usize, String)> = self.nodes.iter().enumerate().filter_map(|(idx, slot)| {
+            let node = match slot.as_ref() {
+                Some(node) => node,
+                None => return None,
+            };
+            if self.cfg.exclude.iter().any(|p| pattern_matches(p, node)) {
+                return Some((idx, node.name.clone()));
+            }
+            if !self.cfg.include.is_empty() && !self.cfg.include.iter().any(|p| pattern_matches(p, node)) {
+                return Some((idx, node.name.clone()));
+            }
+            None
+        }).collect();
+        for (idx, name) in to_remove {
+            debugln!("apply_filters; removing={}", name);
+            self.remove(idx);
+        }
+    }
+
+    /// Remaps every node and edge id into a dense `0..len` range, sweeping
+    /// out the tombstones left behind by `remove`. This is the only place
+    /// ids are renumbered; every mutating pass before it (`remove_orphans`,
+    /// `apply_filters`, `focus`, `retain_cycles_only`, ...) leaves ids stable
+    /// so removal stays a single O(edges) pass instead of O(nodes * edges).
+    fn compact(&mut self) {
+        let mut remap: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut dense = Vec::with_capacity(self.nodes.len());
+        for (old_id, slot) in self.nodes.drain(..).enumerate() {
+            if let Some(dep) = slot {
+                remap[old_id] = Some(dense.len());
+                dense.push(Some(dep));
+            }
+        }
+        self.nodes = dense;
+        for edge in self.edges.iter_mut() {
+            edge.0 = remap[edge.0].expect("compact() runs after orphans/filters have dropped dangling edges");
+            edge.1 = remap[edge.1].expect("compact() runs after orphans/filters have dropped dangling edges");
+        }
+        // `apply_filters`/`retain_cycles_only` can legitimately delete the
+        // root node itself (e.g. an `include` pattern the root doesn't
+        // match); silently remapping to slot 0 would make depth coloring
+        // start from an arbitrary node with no indication anything was
+        // wrong, so re-root explicitly onto the first surviving node instead.
+        let remapped_root = if self.root < remap.len() { remap[self.root] } else { None };
+        self.root = match remapped_root {
+            Some(id) => id,
+            None => {
+                debugln!("compact; root was removed by a filter, re-rooting onto first surviving node");
+                0
+            }
+        };
+    }
+
+    /// BFS distance from `root` to every reachable node, following edges in
+    /// their `parent -> child` direction. Unreachable nodes get `None`; in
+    /// `render_to` that happens to nodes cut off from the root by
+    /// `apply_filters`, and in cycles-only mode, where `remove_orphans` is
+    /// skipped.
+    fn depths_from_root(&self) -> Vec<Option<usize>> {
+        let mut depth = vec![None; self.nodes.len()];
+        if self.root >= self.nodes.len() || self.nodes[self.root].is_none() {
+            return depth;
+        }
+        let adj = self.build_adjacency();
+        depth[self.root] = Some(0);
+        let mut queue = VecDeque::new();
+        queue.push_back(self.root);
+        while let Some(cur) = queue.pop_front() {
+            let d = depth[cur].unwrap();
+            for &child in &adj[cur] {
+                if depth[child].is_none() {
+                    depth[child] = Some(d + 1);
+                    queue.push_back(child);
+                }
+            }
+        }
+        depth
+    }
+
+    /// Looks up the configured fill color for a BFS `depth`, clamping to the
+    /// last palette entry for depths beyond it. `None` if coloring is
+    /// disabled (empty palette).
+    fn depth_color(&self, depth: usize) -> Option<&str> {
+        if self.cfg.depth_colors.is_empty() {
+            return None;
+        }
+        let idx = depth.min(self.cfg.depth_colors.len() - 1);
+        Some(self.cfg.depth_colors[idx].as_str())
+    }
+
+    /// Assigns each node the id of its strongly-connected component, using
+    /// Tarjan's algorithm with an explicit work stack (rather than recursion)
+    /// so it doesn't blow the native stack on large graphs. SCCs are
+    /// numbered in the order they're closed off; every singleton gets its
+    /// own id, and `analyze_cycles` does not count it as cyclic even if it
+    /// still carries a self-edge (as it can when `retain_cycles_only` runs
+    /// before `render_to` has removed self-edges).
+    fn tarjan_scc(&self) -> Vec<usize> {
+        let n = self.nodes.len();
+        let adj = self.build_adjacency();
+
+        let mut index: Vec<Option<usize>> = vec![None; n];
+        let mut lowlink = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut scc_stack: Vec<usize> = vec![];
+        let mut scc_id = vec![0; n];
+        let mut next_index = 0;
+        let mut next_scc = 0;
+
+        for start in 0..n {
+            if index[start].is_some() {
+                continue;
+            }
+
+            // (node, next child offset in adj[node] left to visit)
+            let mut work: Vec<(usize, usize)> = vec![(start, 0)];
+            index[start] = Some(next_index);
+            lowlink[start] = next_index;
+            next_index += 1;
+            scc_stack.push(start);
+            on_stack[start] = true;
+
+            while let Some(&mut (v, ref mut pos)) = work.last_mut() {
+                if *pos < adj[v].len() {
+                    let w = adj[v][*pos];
+                    *pos += 1;
+                    if index[w].is_none() {
+                        index[w] = Some(next_index);
+                        lowlink[w] = next_index;
+                        next_index += 1;
+                        scc_stack.push(w);
+                        on_stack[w] = true;
+                        work.push((w, 0));
+                    } else if on_stack[w] {
+                        lowlink[v] = lowlink[v].min(index[w].unwrap());
+                    }
+                } else {
+                    work.pop();
+                    if let Some(&(parent, _)) = work.last() {
+                        lowlink[parent] = lowlink[parent].min(lowlink[v]);
+                    }
+                    if lowlink[v] == index[v].unwrap() {
+                        loop {
+                            let w = scc_stack.pop().unwrap();
+                            on_stack[w] = false;
+                            scc_id[w] = next_scc;
+                            if w == v {
+                                break;
+                            }
                         }
+                        next_scc += 1;
                     }
-                    removed = true;
-                    break;
                 }
             }
-            if !removed {
-                break;
+        }
+        scc_id
+    }
+
+    /// Returns each node's SCC id alongside whether that SCC is a real cycle
+    /// (size > 1).
+    fn analyze_cycles(&self) -> (Vec<usize>, Vec<bool>) {
+        let scc_id = self.tarjan_scc();
+        let num_sccs = scc_id.iter().cloned().max().map_or(0, |m| m + 1);
+        let mut scc_size = vec![0; num_sccs];
+        for &id in &scc_id {
+            scc_size[id] += 1;
+        }
+        let is_cyclic = scc_id.iter().map(|&id| scc_size[id] > 1).collect();
+        (scc_id, is_cyclic)
+    }
+
+    /// Prunes every node that isn't part of a dependency cycle, leaving only
+    /// the cyclic components. Intended for a `--cycles-only` mode; callers
+    /// opt into this explicitly before `render_to`.
+    pub fn retain_cycles_only(&mut self) {
+        let (_, is_cyclic) = self.analyze_cycles();
+        let to_remove: Vec<usize> = is_cyclic.iter().enumerate()
+            .filter_map(|(i, &cyclic)| if cyclic { None } else { Some(i) })
+            .collect();
+        for idx in to_remove {
+            self.remove(idx);
+        }
+        // `root` is almost certainly one of the nodes just removed (the root
+        // crate is rarely itself cyclic); re-root on any surviving node so
+        // `depths_from_root` still has somewhere sane to start from.
+        if self.root >= self.nodes.len() || self.nodes[self.root].is_none() {
+            if let Some(survivor) = self.nodes.iter().position(|slot| slot.is_some()) {
+                self.root = survivor;
+            }
+        }
+        self.cycles_only = true;
+    }
+
+    /// Narrows the graph down to the subgraph reachable to/from `name`/`ver`
+    /// (its descendants, its ancestors, or both), dropping everything else.
+    /// Returns `false` without changing anything if no such node exists.
+    /// Useful for answering "why is crate X in my tree?" on large graphs;
+    /// this crate exposes it as a library call only, with no command-line
+    /// flag.
+    pub fn focus(&mut self, name: &str, ver: &str, direction: Focus) -> bool {
+        let target = match self.find(name, ver) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let mut keep = vec![false; self.nodes.len()];
+        keep[target] = true;
+        if direction == Focus::Descendants || direction == Focus::Both {
+            let adj = self.build_adjacency();
+            self.mark_reachable(target, &adj, &mut keep);
+        }
+        if direction == Focus::Ancestors || direction == Focus::Both {
+            let radj = self.build_reverse_adjacency();
+            self.mark_reachable(target, &radj, &mut keep);
+        }
+
+        let to_remove: Vec<usize> = keep.iter().enumerate()
+            .filter_map(|(i, &k)| if k { None } else { Some(i) })
+            .collect();
+        for idx in to_remove {
+            self.remove(idx);
+        }
+        // The old root may have just been pruned (e.g. a descendants-only
+        // focus drops everything above the target); re-root on the target
+        // itself, which `keep` guarantees always survives.
+        if self.nodes[self.root].is_none() {
+            self.root = target;
+        }
+        true
+    }
+
+    /// BFS from `start` over a prebuilt adjacency list (forward or reverse,
+    /// the caller's choice), marking every reached node in `keep`.
+    fn mark_reachable(&self, start: usize, adj: &[Vec<usize>], keep: &mut Vec<bool>) {
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(cur) = queue.pop_front() {
+            for &to in &adj[cur] {
+                if !keep[to] {
+                    keep[to] = true;
+                    queue.push_back(to);
+                }
             }
         }
     }
@@ -167,39 +478,25 @@ impl<'c, 'o> DepGraph<'c, 'o> {
         }
     }
 
+    /// Marks the given crate as the root that `remove_orphans` and the depth
+    /// coloring BFS start from. With stable node ids there's no need to
+    /// physically move it to slot 0 any more - we just remember its id.
     pub fn set_root(&mut self, name: &str, ver: &str) -> bool {
-        let root_id = if let Some(i) = self.find(name, ver) {
-            i
-        } else {
-            return false;
-        };
-        if root_id == 0 {
-            return true;
-        }
-
-        // Swap with 0
-        self.nodes.swap(0, root_id);
-
-        // Adjust edges
-        for edge in self.edges.iter_mut() {
-            if edge.0 == 0 {
-                edge.0 = root_id;
-            } else if edge.0 == root_id {
-                edge.0 = 0;
-            }
-            if edge.1 == 0 {
-                edge.1 = root_id;
-            } else if edge.1 == root_id {
-                edge.1 = 0;
+        match self.find(name, ver) {
+            Some(id) => {
+                self.root = id;
+                true
             }
+            None => false,
         }
-        true
     }
 
     pub fn find(&self, name: &str, ver: &str) -> Option<usize> {
-        for (i, d) in self.nodes.iter().enumerate() {
-            if d.name == name && d.ver == ver {
-                return Some(i);
+        for (i, slot) in self.nodes.iter().enumerate() {
+            if let Some(d) = slot.as_ref() {
+                if d.name == name && d.ver == ver {
+                    return Some(i);
+                }
             }
         }
         None
@@ -209,7 +506,7 @@ impl<'c, 'o> DepGraph<'c, 'o> {
         if let Some(i) = self.find(name, ver) {
             return i;
         }
-        self.nodes.push(ResolvedDep::new(name.to_owned(), ver.to_owned()));
+        self.nodes.push(Some(ResolvedDep::new(name.to_owned(), ver.to_owned())));
         self.nodes.len() - 1
     }
 
@@ -217,86 +514,127 @@ impl<'c, 'o> DepGraph<'c, 'o> {
         debugln!("exec=render_to;");
         self.edges.sort();
         self.edges.dedup();
-        self.remove_orphans();
-        self.remove_self_pointing();
-
-        // nipunn-mbp:nucleus nipunn$ find . -name Cargo.toml | xargs grep --no-filename "name =" | sed 's/name = //' | sed 's/$/,/' | sort -u
-        let impt = vec![
-            "app_interface",
-            "async",
-            "backoff",
-            "bitslab",
-            "canopy",
-            "canopy_check",
-            "casefold",
-            "common",
-            "config",
-            "cyclotron",
-            "database",
-            "dbx-collections",
-            "debug_enum_int_derive",
-            "diff",
-            "disk_usage_manager",
-            "dynamic_loader",
-            "environment",
-            "event_queue",
-            "events",
-            "events_derive",
-            "fileid_manager",
-            "filename",
-            "fs",
-            "heirloom",
-            "hello_world",
-            "http2_connection",
-            "intent_manager",
-            "mount_table",
-            "network",
-            "ntdll",
-            "nucleus_c_api",
-            "nucleus_engine",
-            "pb_service",
-            "planning",
-            "pre_local",
-            "prost",
-            "protocol",
-            "resync",
-            "rpc_shim",
-            "sawmill",
-            "scripts",
-            "startup",
-            "testing",
-            "transport_adapter",
-            "tree",
-            "trinity",
-        ];
-        let unimpt_idxs: Vec<usize> = self.nodes.iter().enumerate().filter_map(|(idx, node)| {
-            if node.name.contains("proto_") {
-                Some(idx)
-            } else if impt.contains(&node.name.as_str()) {
-                None
-            } else {
-                Some(idx)
-            }
-        }).collect();
-        if env::var("DONT_SKIP").is_err() {
-            for (which, idx) in unimpt_idxs.into_iter().enumerate() {
-                eprintln!("Removing {}", self.nodes[idx - which].name);
-                self.remove(idx - which);
-            }
+        if self.cycles_only {
+            // The retained cycles are generally several components disjoint
+            // from `root` (the root crate is rarely itself cyclic), so the
+            // normal single-root orphan BFS would treat all of them as
+            // unreachable and wipe the graph; `retain_cycles_only` already
+            // did the pruning we need.
+            debugln!("render_to; cycles_only, skipping remove_orphans");
+        } else {
+            self.remove_orphans();
         }
+        self.remove_self_pointing();
+        self.apply_filters();
+        self.compact();
 
         debugln!("dg={:#?}", self);
+        let depths = self.depths_from_root();
+        let (scc_id, is_cyclic) = self.analyze_cycles();
         try!(writeln!(output, "{}", "digraph dependencies {"));
-        for (i, dep) in self.nodes.iter().enumerate() {
+        for (i, slot) in self.nodes.iter().enumerate() {
+            let dep = slot.as_ref().expect("compact() leaves no tombstones");
             try!(write!(output, "\tN{}", i));
-            try!(dep.label(output, self.cfg));
+            let color = if is_cyclic[i] {
+                Some(self.cfg.cycle_color.as_str())
+            } else {
+                depths[i].and_then(|d| self.depth_color(d))
+            };
+            try!(dep.label(output, self.cfg, color));
         }
         for ed in &self.edges {
             try!(write!(output, "\t{}", ed));
-            try!(ed.label(output, &self));
+            let cyclic_edge = is_cyclic[ed.0] && scc_id[ed.0] == scc_id[ed.1];
+            try!(ed.label(output, &self, cyclic_edge));
         }
         try!(writeln!(output, "{}", "}"));
         Ok(())
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+
+    #[test]
+    fn glob_match_exact() {
+        assert!(glob_match("serde", "serde"));
+        assert!(!glob_match("serde", "serde_json"));
+    }
+
+    #[test]
+    fn glob_match_star_wildcard() {
+        assert!(glob_match("serde*", "serde_json"));
+        assert!(glob_match("*_json", "serde_json"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("ser*json", "serde_json"));
+        assert!(!glob_match("serde*", "tokio"));
+    }
+
+    #[test]
+    fn glob_match_question_mark() {
+        assert!(glob_match("serde_????", "serde_json"));
+        assert!(!glob_match("serde_???", "serde_json"));
+    }
+
+    #[test]
+    fn analyze_cycles_distinguishes_singletons_from_a_real_cycle() {
+        let cfg = Config::new();
+        let mut dg = DepGraph::new(&cfg);
+        let root = dg.find_or_add("root", "1.0");
+        let a = dg.add_child(root, "a", "1.0");
+        let b = dg.add_child(a, "b", "1.0");
+        dg.edges.push(Ed(b, a));
+
+        let (_, is_cyclic) = dg.analyze_cycles();
+
+        assert!(!is_cyclic[root]);
+        assert!(is_cyclic[a]);
+        assert!(is_cyclic[b]);
+    }
+
+    #[test]
+    fn focus_ancestors_keeps_only_the_path_up_to_target() {
+        let cfg = Config::new();
+        let mut dg = DepGraph::new(&cfg);
+        let root = dg.find_or_add("root", "1.0");
+        dg.set_root("root", "1.0");
+        let mid = dg.add_child(root, "mid", "1.0");
+        let target = dg.add_child(mid, "target", "1.0");
+        dg.add_child(target, "child_of_target", "1.0");
+        dg.add_child(root, "unrelated", "1.0");
+
+        assert!(dg.focus("target", "1.0", Focus::Ancestors));
+
+        assert!(dg.get(root).is_some());
+        assert!(dg.get(mid).is_some());
+        assert!(dg.get(target).is_some());
+        assert!(dg.find("child_of_target", "1.0").is_none());
+        assert!(dg.find("unrelated", "1.0").is_none());
+    }
+
+    #[test]
+    fn retain_cycles_only_survives_render_to() {
+        let cfg = Config::new();
+        let mut dg = DepGraph::new(&cfg);
+        let root = dg.find_or_add("root", "1.0");
+        dg.set_root("root", "1.0");
+        let a = dg.add_child(root, "a", "1.0");
+        let b = dg.add_child(a, "b", "1.0");
+        dg.edges.push(Ed(b, a));
+        dg.find_or_add("unrelated", "1.0");
+
+        dg.retain_cycles_only();
+
+        let mut out = Vec::new();
+        dg.render_to(&mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+
+        assert!(dot.contains("a 1.0"));
+        assert!(dot.contains("b 1.0"));
+        assert!(!dot.contains("root 1.0"));
+        assert!(!dot.contains("unrelated 1.0"));
+    }
+}